@@ -4,6 +4,7 @@ use std::time::{Duration, Instant};
 
 use clap::Parser;
 use crossterm::event::{self, Event as CEvent, KeyCode};
+use gossip::node::{GossipEvent, GossipNode};
 use rand::seq::SliceRandom;
 use ratatui::layout::{Constraint, Direction, Layout};
 use ratatui::style::{Color, Style};
@@ -13,11 +14,6 @@ use ratatui::widgets::{Block, Borders, Gauge};
 use ratatui::{Terminal, backend::CrosstermBackend};
 use tokio::sync::broadcast;
 
-#[derive(Debug, Clone)]
-enum GossipEvent {
-    RumorReceived { node_id: String },
-}
-
 #[derive(Debug, Clone)]
 enum Event {
     Step,
@@ -39,6 +35,18 @@ struct Opts {
     /// Interval (ms) between infections
     #[arg(long, default_value_t = 1000)]
     step_ms: u64,
+
+    /// Drive the visualizer off a small in-process cluster of real
+    /// `GossipNode`s instead of the simulated infection loop. Nodes are wired
+    /// to each other over an in-memory transport rather than genuine
+    /// Maelstrom RPC (that needs a `maelstrom::Runtime` per node, driven over
+    /// stdio by the Maelstrom test harness, which this standalone binary has
+    /// no way to construct) — but each node keeps its own independent CRDS
+    /// store, so the rumor genuinely has to propagate hop by hop to reach
+    /// every node. With `--break`, each push round waits for a single step
+    /// instead of firing on a timer.
+    #[arg(long, default_value_t = false)]
+    live: bool,
 }
 
 struct State {
@@ -61,24 +69,35 @@ async fn main() -> anyhow::Result<()> {
     let opts = Opts::parse();
     let (tx, _rx) = broadcast::channel(16);
 
-    spawn(
-        opts.nodes.clone(),
-        opts.step_ms,
-        tx.clone(),
-        opts.break_mode,
-    );
+    if opts.live {
+        spawn_live(
+            opts.nodes.clone(),
+            opts.step_ms,
+            tx.clone(),
+            opts.break_mode,
+        );
+    } else {
+        spawn(
+            opts.nodes.clone(),
+            opts.step_ms,
+            tx.clone(),
+            opts.break_mode,
+        );
+    }
 
     run(opts.nodes, tx.clone(), opts.break_mode)?;
     Ok(())
 }
 
-// TODO: replace this with real gossip-core events
+// Simulated infection loop, kept as the default so the visualizer still
+// works standalone without a gossip cluster to attach to.
 fn spawn(mut nodes: Vec<String>, step_ms: u64, tx: broadcast::Sender<Event>, break_mode: bool) {
     use rand::rng;
 
     // shuffle before the async block (ThreadRng is !Send)
     nodes.shuffle(&mut rng());
     let mut rx = tx.subscribe();
+    let mut next_message = 0u64;
 
     tokio::spawn(async move {
         while !nodes.is_empty() {
@@ -102,12 +121,81 @@ fn spawn(mut nodes: Vec<String>, step_ms: u64, tx: broadcast::Sender<Event>, bre
             for id in &batch {
                 let _ = tx.send(Event::Gossip(GossipEvent::RumorReceived {
                     node_id: id.to_string(),
+                    message: next_message,
                 }));
+                next_message += 1;
             }
         }
     });
 }
 
+// The fanout used when pushing a rumor onward in `spawn_live`, mirroring
+// `gossip::node`'s `RANDOM_PEER_COUNT` without depending on that private
+// constant.
+const LIVE_FANOUT: usize = 3;
+
+// Drives the visualizer off a small in-process cluster of real `GossipNode`s
+// instead of the simulation above. The nodes aren't attached to a Maelstrom
+// cluster here, so they talk to each other over an in-memory transport:
+// `GossipNode::inject` stands in for the `Broadcast` RPC a real
+// `maelstrom::Runtime` would carry, since this standalone binary has no
+// stdio harness to drive one. Each node still keeps its own independent CRDS
+// store, so a rumor genuinely has to propagate hop by hop — a node only
+// pushes a message onward the first time it actually receives it, same as
+// `start_push_loop` does for a real node, just without the Runtime-bound
+// ack/retry/weighting machinery.
+fn spawn_live(nodes: Vec<String>, step_ms: u64, tx: broadcast::Sender<Event>, break_mode: bool) {
+    use rand::rng;
+
+    let cluster: Vec<GossipNode> = nodes
+        .iter()
+        .map(|id| {
+            let neighbours = nodes.iter().filter(|n| *n != id).cloned().collect();
+            GossipNode::new(neighbours)
+        })
+        .collect();
+
+    for (i, node) in cluster.iter().cloned().enumerate() {
+        let nodes = nodes.clone();
+        let cluster = cluster.clone();
+        let peer_count = cluster.len();
+        let mut events = node.subscribe();
+        let mut steps = tx.subscribe();
+        let tx = tx.clone();
+
+        tokio::spawn(async move {
+            while let Ok(event) = events.recv().await {
+                if let GossipEvent::RumorReceived { message, .. } = event {
+                    if break_mode {
+                        loop {
+                            match steps.recv().await {
+                                Ok(Event::Step) => break,
+                                Ok(_) => continue,
+                                Err(_) => return,
+                            }
+                        }
+                    } else {
+                        tokio::time::sleep(Duration::from_millis(step_ms)).await;
+                    }
+
+                    // fresh `rng()` each round, never held across the
+                    // `.await`s above (`ThreadRng` is `!Send`, so holding it
+                    // across an await point would make this future `!Send`)
+                    let mut rng = rng();
+                    let targets: Vec<usize> = (0..peer_count).filter(|&j| j != i).collect();
+                    for &j in targets.choose_multiple(&mut rng, LIVE_FANOUT.min(targets.len())) {
+                        cluster[j].inject(&nodes[j], message);
+                    }
+                }
+
+                let _ = tx.send(Event::Gossip(event));
+            }
+        });
+    }
+
+    cluster[0].inject(&nodes[0], 0);
+}
+
 fn run(node_ids: Vec<String>, tx: broadcast::Sender<Event>, break_mode: bool) -> io::Result<()> {
     crossterm::terminal::enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -141,8 +229,14 @@ fn run(node_ids: Vec<String>, tx: broadcast::Sender<Event>, break_mode: bool) ->
 
         // apply incoming events
         while let Ok(ev) = rx.try_recv() {
-            if let Event::Gossip(GossipEvent::RumorReceived { node_id }) = ev {
-                state.infected.insert(node_id);
+            match ev {
+                Event::Gossip(GossipEvent::RumorReceived { node_id, .. }) => {
+                    state.infected.insert(node_id);
+                }
+                Event::Gossip(GossipEvent::Acked { .. })
+                | Event::Gossip(GossipEvent::Pruned { .. })
+                | Event::Gossip(GossipEvent::PullMerged { .. })
+                | Event::Step => {}
             }
         }
 
@@ -0,0 +1,102 @@
+//! A compact Bloom filter used to summarize a node's CRDS store keys for pull-based
+//! anti-entropy, modeled on the CRDS filters used by Solana's gossip pull protocol.
+//!
+//! False positives only ever cause a value to be considered "already present"
+//! when it isn't, which at worst makes a `PullResponse` omit a value for one
+//! round; they never cause a spurious value to be reported as missing. That
+//! asymmetry is what keeps the pull protocol correct (just eventually, rather
+//! than immediately, convergent).
+
+use serde::{Deserialize, Serialize};
+use std::hash::{Hash, Hasher};
+
+/// Target false-positive rate used to size new filters.
+const FALSE_POSITIVE_RATE: f64 = 0.01;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BloomFilter {
+    bits: Vec<bool>,
+    num_hashes: u32,
+}
+
+impl BloomFilter {
+    /// Builds a filter sized for `num_items` entries at `FALSE_POSITIVE_RATE`.
+    pub fn with_capacity(num_items: usize) -> Self {
+        let num_items = num_items.max(1);
+        let num_bits = Self::optimal_num_bits(num_items, FALSE_POSITIVE_RATE);
+        let num_hashes = Self::optimal_num_hashes(num_bits, num_items);
+        BloomFilter {
+            bits: vec![false; num_bits],
+            num_hashes,
+        }
+    }
+
+    pub fn insert(&mut self, value: u64) {
+        for i in 0..self.num_hashes {
+            let idx = self.index_for(value, i);
+            self.bits[idx] = true;
+        }
+    }
+
+    pub fn contains(&self, value: u64) -> bool {
+        (0..self.num_hashes).all(|i| self.bits[self.index_for(value, i)])
+    }
+
+    /// Builds a filter containing every value in `values`, sized for the set.
+    pub fn from_values<I: IntoIterator<Item = u64>>(values: I) -> Self {
+        let values: Vec<u64> = values.into_iter().collect();
+        let mut filter = BloomFilter::with_capacity(values.len());
+        for value in values {
+            filter.insert(value);
+        }
+        filter
+    }
+
+    fn index_for(&self, value: u64, seed: u32) -> usize {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        value.hash(&mut hasher);
+        seed.hash(&mut hasher);
+        (hasher.finish() as usize) % self.bits.len()
+    }
+
+    fn optimal_num_bits(num_items: usize, false_positive_rate: f64) -> usize {
+        let n = num_items as f64;
+        let m = -(n * false_positive_rate.ln()) / (std::f64::consts::LN_2.powi(2));
+        (m.ceil() as usize).max(8)
+    }
+
+    fn optimal_num_hashes(num_bits: usize, num_items: usize) -> u32 {
+        let m = num_bits as f64;
+        let n = num_items as f64;
+        (((m / n) * std::f64::consts::LN_2).round() as u32).max(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contains_every_inserted_value() {
+        let values = [1u64, 2, 3, 42, 100];
+        let filter = BloomFilter::from_values(values);
+        for value in values {
+            assert!(filter.contains(value));
+        }
+    }
+
+    #[test]
+    fn empty_filter_contains_nothing() {
+        let filter = BloomFilter::with_capacity(16);
+        for value in 0u64..64 {
+            assert!(!filter.contains(value));
+        }
+    }
+
+    #[test]
+    fn sizing_grows_with_item_count() {
+        let small = BloomFilter::with_capacity(1);
+        let large = BloomFilter::with_capacity(10_000);
+        assert!(large.bits.len() > small.bits.len());
+    }
+}
@@ -0,0 +1,153 @@
+//! A minimal CRDS (cluster replicated data store): a keyed last-write-wins
+//! map, the same convergence model Solana's gossip uses to let any key be
+//! overwritten network-wide instead of only ever accumulating immutable ids.
+
+use serde::{Deserialize, Serialize};
+use std::hash::{Hash, Hasher};
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Versioned<T> {
+    pub value: T,
+    pub version: u64,
+    pub wallclock: u64,
+}
+
+impl Versioned<Vec<u8>> {
+    /// Whether `self` should replace `existing` under the CRDS merge rule:
+    /// the higher `(version, wallclock)` pair wins, with ties broken
+    /// deterministically by the value's hash so every replica converges on
+    /// the same winner without a tiebreak authority.
+    pub fn wins_over(&self, existing: Option<&Versioned<Vec<u8>>>) -> bool {
+        match existing {
+            None => true,
+            Some(current) => {
+                (self.version, self.wallclock, hash_value(&self.value))
+                    > (current.version, current.wallclock, hash_value(&current.value))
+            }
+        }
+    }
+}
+
+fn hash_value(value: &[u8]) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Hashes a CRDS key into the same u64 domain used by message ids, so a
+/// single `BloomFilter` can summarize both during pull-based anti-entropy.
+pub fn hash_key(key: &str) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The store's real key namespace: a numeric broadcast id or a named
+/// `Upsert` key, kept as distinct variants rather than both being plain
+/// strings so a named key that happens to look like a number (e.g. `"42"`)
+/// can never collide with, or be surfaced as, a broadcast message id.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum StoreKey {
+    Message(u64),
+    Named(String),
+}
+
+impl StoreKey {
+    /// The u64 domain a `BloomFilter` summarizes this entry under: a message
+    /// id is used directly, since filters already carry message ids in that
+    /// domain, while a named key is hashed via `hash_key` as before.
+    pub fn digest(&self) -> u64 {
+        match self {
+            StoreKey::Message(id) => *id,
+            StoreKey::Named(key) => hash_key(key),
+        }
+    }
+
+    /// The key's wire representation where it's already reported in a
+    /// namespace-specific list (e.g. `PullResponse`'s `entries`, which only
+    /// ever carries `Named` keys alongside a separate `messages: Vec<u64>`):
+    /// a message id's decimal string form, or the named key unchanged. Not
+    /// safe to use where both variants share one flat keyspace — see
+    /// `namespaced`.
+    pub fn as_external(&self) -> String {
+        match self {
+            StoreKey::Message(id) => id.to_string(),
+            StoreKey::Named(key) => key.clone(),
+        }
+    }
+
+    /// The key's wire representation for a flat keyspace that mixes both
+    /// variants, e.g. `ReadOk`'s combined `store` map: a `"msg:"`/`"key:"`
+    /// prefix keeps a message id and a same-looking named key (`"msg:42"` vs.
+    /// `Upsert{key: "42"}`) from colliding once they're both just map keys,
+    /// which `as_external` alone would let happen.
+    pub fn namespaced(&self) -> String {
+        match self {
+            StoreKey::Message(id) => format!("msg:{id}"),
+            StoreKey::Named(key) => format!("key:{key}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(version: u64, wallclock: u64, value: &[u8]) -> Versioned<Vec<u8>> {
+        Versioned {
+            value: value.to_vec(),
+            version,
+            wallclock,
+        }
+    }
+
+    #[test]
+    fn anything_wins_over_an_absent_entry() {
+        assert!(entry(0, 0, b"").wins_over(None));
+    }
+
+    #[test]
+    fn higher_version_always_wins() {
+        let incoming = entry(2, 0, b"b");
+        let current = entry(1, 100, b"a");
+        assert!(incoming.wins_over(Some(&current)));
+        assert!(!current.wins_over(Some(&incoming)));
+    }
+
+    #[test]
+    fn wallclock_breaks_a_version_tie() {
+        let incoming = entry(1, 5, b"b");
+        let current = entry(1, 3, b"a");
+        assert!(incoming.wins_over(Some(&current)));
+        assert!(!current.wins_over(Some(&incoming)));
+    }
+
+    #[test]
+    fn hash_breaks_a_version_and_wallclock_tie_without_favoring_either_side() {
+        let a = entry(1, 1, b"a");
+        let b = entry(1, 1, b"b");
+        // exactly one side wins, consistently, regardless of which one is
+        // treated as "incoming" vs "existing"
+        assert_ne!(a.wins_over(Some(&b)), b.wins_over(Some(&a)));
+    }
+
+    #[test]
+    fn a_named_key_that_looks_numeric_never_equals_a_message_key() {
+        let named = StoreKey::Named("42".to_string());
+        let message = StoreKey::Message(42);
+        assert_ne!(named, message);
+    }
+
+    #[test]
+    fn as_external_yields_the_bare_wire_representation() {
+        assert_eq!(StoreKey::Message(42).as_external(), "42");
+        assert_eq!(StoreKey::Named("weight".to_string()).as_external(), "weight");
+    }
+
+    #[test]
+    fn namespaced_keeps_a_same_looking_message_and_named_key_distinct() {
+        let message = StoreKey::Message(42).namespaced();
+        let named = StoreKey::Named("42".to_string()).namespaced();
+        assert_ne!(message, named);
+    }
+}
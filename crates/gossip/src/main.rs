@@ -1,6 +1,8 @@
 use maelstrom::{Result, Runtime};
 use std::sync::Arc;
 
+pub mod bloom;
+pub mod crds;
 pub mod node;
 pub mod request;
 use crate::node::GossipNode;
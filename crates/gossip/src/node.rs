@@ -1,16 +1,109 @@
+use crate::bloom::BloomFilter;
+use crate::crds::{StoreKey, Versioned};
 use crate::request::Request;
 use async_trait::async_trait;
 use log::info;
 use maelstrom::protocol::Message;
 use maelstrom::{Node as MaelstromNode, Result, Runtime};
-use rand::seq::SliceRandom;
+use rand::Rng;
+use rand::seq::{IteratorRandom, SliceRandom};
 use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::sync::broadcast;
 use tokio::time::{Duration, sleep};
 
+/// How many events the live event channel buffers before lagging
+/// subscribers (e.g. the TUI visualizer) start missing the oldest ones.
+const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
 /// The number of random peers to select for gossiping.
 const RANDOM_PEER_COUNT: usize = 3;
 
+/// How often each node picks a random neighbour and pulls whatever it's
+/// missing, so recovered/partitioned nodes eventually reconcile their store
+/// instead of relying solely on push-based `Broadcast`.
+const PULL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The size of the stable subset of neighbours a node actively pushes to.
+const PUSH_FANOUT: usize = 3;
+
+/// How long a message may sit unacked before we consider a push peer timed
+/// out. The active push set is rotated every `PUSH_MSG_TIMEOUT / 2`.
+const PUSH_MSG_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long a verified pong stays valid before a peer needs to be re-pinged.
+const PING_TTL: Duration = Duration::from_secs(10);
+
+/// How long we wait for a `Pong` before treating a peer as unreachable.
+const PING_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// How often peers marked unreachable are re-pinged. The regular push/pull
+/// fanout filters unreachable peers out before ever calling `ensure_live` on
+/// them, so without this a peer that recovers would stay excluded forever.
+const REPING_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Weighted random sampling without replacement, via the Efraimidis–Spirakis
+/// algorithm: each candidate draws a key `u^(1/w)` for `u` uniform in (0, 1],
+/// and the `count` candidates with the largest keys are kept. This samples
+/// each peer with probability proportional to its weight while still giving
+/// every peer a chance, so reliable peers are favoured without starving the
+/// rest of the fanout.
+fn weighted_sample(
+    candidates: &[String],
+    weights: &HashMap<String, u64>,
+    count: usize,
+    rng: &mut impl Rng,
+) -> Vec<String> {
+    let mut keyed: Vec<(f64, &String)> = candidates
+        .iter()
+        .map(|peer| {
+            let weight = *weights.get(peer).unwrap_or(&1) as f64;
+            let u: f64 = 1.0 - rng.random::<f64>();
+            (u.powf(1.0 / weight), peer)
+        })
+        .collect();
+    keyed.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
+    keyed
+        .into_iter()
+        .take(count)
+        .map(|(_, peer)| peer.clone())
+        .collect()
+}
+
+/// The message ids represented in the store, i.e. the keys written by
+/// `try_add`'s thin `Broadcast` -> `Upsert` wrapper (`StoreKey::Message`,
+/// version 0). This is a derived view rather than a separately tracked set,
+/// so there's exactly one place a message can live.
+fn numeric_messages(store: &HashMap<StoreKey, Versioned<Vec<u8>>>) -> Vec<u64> {
+    store
+        .keys()
+        .filter_map(|key| match key {
+            StoreKey::Message(id) => Some(*id),
+            StoreKey::Named(_) => None,
+        })
+        .collect()
+}
+
+/// Real gossip signals emitted as they happen, so a consumer (e.g. the TUI
+/// visualizer) can observe genuine protocol activity instead of a simulation.
+#[derive(Debug, Clone)]
+pub enum GossipEvent {
+    RumorReceived { node_id: String, message: u64 },
+    Acked { peer: String },
+    Pruned { peer: String },
+    PullMerged { count: usize },
+}
+
+/// Tracks liveness for a single peer: the last time it verified itself with a
+/// matching `Pong`, and an outstanding `Ping` we're waiting on, if any.
+#[derive(Clone, Default)]
+pub struct PingEntry {
+    pub last_pong: Option<Instant>,
+    pub pending_token: Option<u64>,
+    pub pending_since: Option<Instant>,
+}
+
 #[derive(Clone, Default)]
 pub struct GossipNode {
     pub state: Arc<Mutex<NodeState>>,
@@ -19,25 +112,191 @@ pub struct GossipNode {
 impl GossipNode {
     pub fn new(neighbours: Vec<String>) -> Self {
         GossipNode {
-            state: Arc::new(Mutex::new(NodeState {
-                seen: HashSet::new(),
-                neighbours,
-                unacked: HashMap::new(),
-            })),
+            state: Arc::new(Mutex::new(NodeState::new(neighbours))),
         }
     }
+
+    /// Subscribes to this node's live gossip events, e.g. to drive the TUI
+    /// visualizer off real protocol activity instead of a simulation.
+    pub fn subscribe(&self) -> broadcast::Receiver<GossipEvent> {
+        self.state.lock().unwrap().events.subscribe()
+    }
+
+    /// Originates a rumor directly, bypassing the Maelstrom RPC layer. Used
+    /// by out-of-band drivers (e.g. the TUI visualizer running standalone)
+    /// to seed gossip on a node that isn't attached to a live cluster.
+    pub fn inject(&self, node_id: &str, message: u64) -> bool {
+        self.try_add(message, node_id)
+    }
 }
 
-#[derive(Clone, Default)]
+#[derive(Clone)]
 pub struct NodeState {
-    // a list of all the seen MessageIds
-    pub seen: HashSet<u64>,
-
     // all the neighbours to a node
     pub neighbours: Vec<String>,
 
     // a map of a messageId, and a unique set of neighbours who have not acknowleded it.
     pub unacked: HashMap<u64, HashSet<String>>,
+
+    // per (message, peer) time of the most recent send, so the push loop can
+    // tell a peer that's genuinely timed out from one that's simply still
+    // within its ack window.
+    pub send_times: HashMap<u64, HashMap<String, Instant>>,
+
+    // the subset of neighbours we actively push to; peers that report us as a
+    // redundant path (via `Prune`) are removed from here, distinct from `neighbours`.
+    pub push_peers: HashSet<String>,
+
+    // per-peer reliability score used to bias fanout selection; peers default
+    // to a weight of 1 when absent from this map.
+    pub weights: HashMap<String, u64>,
+
+    // the stable subset of `push_peers` (size `PUSH_FANOUT`) that messages are
+    // actually pushed to; rotated periodically to keep the overlay fresh.
+    pub active_push_peers: HashSet<String>,
+
+    // per-peer ping/pong liveness state.
+    pub ping_cache: HashMap<String, PingEntry>,
+
+    // peers that didn't pong within `PING_TIMEOUT`; excluded from fanout and
+    // unacked retries until they respond to a fresh ping.
+    pub unreachable: HashSet<String>,
+
+    // the generalized last-write-wins CRDS store; numeric broadcasts live
+    // here too, as version-0 entries keyed by `StoreKey::Message` rather
+    // than sharing the `StoreKey::Named` keyspace arbitrary `Upsert`s use.
+    pub store: HashMap<StoreKey, Versioned<Vec<u8>>>,
+
+    // whether the long-lived background tasks (pull, rotation, push loop,
+    // re-ping) have already been spawned; `Topology` can be delivered more
+    // than once, and without this guard a repeat delivery would spawn a
+    // second full set of loops racing the first over the same `NodeState`.
+    pub background_tasks_started: bool,
+
+    // live feed of real gossip signals for observers like the TUI visualizer.
+    pub events: broadcast::Sender<GossipEvent>,
+}
+
+impl NodeState {
+    fn new(neighbours: Vec<String>) -> Self {
+        let (events, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        NodeState {
+            neighbours,
+            unacked: HashMap::new(),
+            send_times: HashMap::new(),
+            push_peers: HashSet::new(),
+            weights: HashMap::new(),
+            active_push_peers: HashSet::new(),
+            ping_cache: HashMap::new(),
+            unreachable: HashSet::new(),
+            store: HashMap::new(),
+            background_tasks_started: false,
+            events,
+        }
+    }
+
+    /// Drops `peer` from every outstanding `unacked` target set and its
+    /// `send_times`, e.g. because it was pruned or rotated out of the active
+    /// push set and will never ack a message again.
+    fn drop_unacked_target(&mut self, peer: &str) {
+        self.unacked.retain(|_, targets| {
+            targets.remove(peer);
+            !targets.is_empty()
+        });
+        for send_times in self.send_times.values_mut() {
+            send_times.remove(peer);
+        }
+    }
+
+    /// Removes `peer` from the active push set because it reported our send
+    /// as redundant, emitting a `Pruned` event if it was actually there, and
+    /// drops it from outstanding unacked bookkeeping since a pruned peer
+    /// already had the message and will never ack it.
+    fn apply_prune(&mut self, peer: &str) {
+        if self.push_peers.remove(peer) {
+            let _ = self.events.send(GossipEvent::Pruned {
+                peer: peer.to_string(),
+            });
+        }
+        self.active_push_peers.remove(peer);
+        self.drop_unacked_target(peer);
+    }
+
+    /// Evicts one random peer from the active push set and admits a fresh
+    /// candidate drawn from `neighbours`, keeping the overlay fresh as the
+    /// topology changes. Candidates include peers pruned for a redundant
+    /// push path, so a pruned peer is lazily re-admitted (into both
+    /// `push_peers` and `active_push_peers`) the next time it's rotated in,
+    /// rather than staying excluded forever.
+    fn rotate_active_push_peer(&mut self, rng: &mut impl Rng) {
+        let current: Vec<String> = self.active_push_peers.iter().cloned().collect();
+        if let Some(evicted) = current.choose(rng).cloned() {
+            self.active_push_peers.remove(&evicted);
+            self.drop_unacked_target(&evicted);
+        }
+
+        let candidates: Vec<String> = self
+            .neighbours
+            .iter()
+            .filter(|peer| !self.active_push_peers.contains(*peer) && !self.unreachable.contains(*peer))
+            .cloned()
+            .collect();
+
+        if let Some(fresh) = weighted_sample(&candidates, &self.weights, 1, rng)
+            .into_iter()
+            .next()
+        {
+            self.push_peers.insert(fresh.clone());
+            self.active_push_peers.insert(fresh);
+        }
+    }
+
+    /// Applies a `BroadcastOk` ack for `message` from `peer`: clears it from
+    /// the unacked target set, dropping the message's bookkeeping entirely
+    /// once every target has acked, and clears its per-peer send time.
+    fn apply_ack(&mut self, message: u64, peer: &str) {
+        if let Some(targets) = self.unacked.get_mut(&message) {
+            targets.remove(peer);
+            if targets.is_empty() {
+                self.unacked.remove(&message);
+                self.send_times.remove(&message);
+            }
+        }
+        if let Some(send_times) = self.send_times.get_mut(&message) {
+            send_times.remove(peer);
+        }
+    }
+
+    /// Whether `peer` has verified itself with a `Pong` within `PING_TTL`.
+    fn is_reachable(&self, peer: &str) -> bool {
+        self.ping_cache
+            .get(peer)
+            .and_then(|entry| entry.last_pong)
+            .is_some_and(|last_pong| last_pong.elapsed() < PING_TTL)
+    }
+
+    /// Whether a `Ping` is already outstanding to `peer` and hasn't yet timed
+    /// out, so the caller should keep waiting rather than send another.
+    fn has_live_pending_ping(&self, peer: &str) -> bool {
+        self.ping_cache
+            .get(peer)
+            .and_then(|entry| entry.pending_since)
+            .is_some_and(|since| since.elapsed() <= PING_TIMEOUT)
+    }
+
+    /// Records that a fresh `Ping` carrying `token` is now outstanding to
+    /// `peer`.
+    fn begin_ping(&mut self, peer: &str, token: u64) {
+        let entry = self.ping_cache.entry(peer.to_string()).or_default();
+        entry.pending_token = Some(token);
+        entry.pending_since = Some(Instant::now());
+    }
+}
+
+impl Default for NodeState {
+    fn default() -> Self {
+        NodeState::new(Vec::new())
+    }
 }
 
 #[async_trait]
@@ -46,83 +305,593 @@ impl MaelstromNode for GossipNode {
         let msg: Result<Request> = req.body.as_obj();
         match msg {
             Ok(Request::Read {}) => {
-                let data = self.snapshot();
-                let msg = Request::ReadOk { messages: data };
+                let (messages, store) = self.snapshot();
+                let msg = Request::ReadOk { messages, store };
                 return runtime.reply(req, msg).await;
             }
             Ok(Request::Broadcast { message }) => {
                 let sender: String = req.src.clone();
-                if self.try_add(message) {
+                // the broadcast workload's client sends `Broadcast` directly
+                // to nodes, so `sender` is only sometimes a gossip peer; the
+                // `BroadcastOk`/`Prune` RPCs below are peer-only protocol and
+                // would read as an unsolicited, uncorrelated message to a
+                // client, which only ever gets the standard `reply_ok`.
+                let sender_is_peer = self.state.lock().unwrap().neighbours.contains(&sender);
+                if self.try_add(message, runtime.node_id()) {
                     let mut state = self.state.lock().unwrap();
 
-                    // before we send a message we move it to unacked
-                    let mut neighbours: HashSet<String> =
-                        state.neighbours.iter().cloned().collect();
-                    neighbours.remove(&sender);
-
-                    // unacked state used to actually send a message to those nodes
-                    state.unacked.insert(message, neighbours.clone());
-                    self.retry(runtime.clone(), message);
+                    // before we send a message we move it to unacked, targeting
+                    // only the active push set; the long-lived push loop
+                    // retries it from here on, but we don't wait for its next
+                    // tick to fire the first send
+                    let mut targets = state.active_push_peers.clone();
+                    targets.remove(&sender);
+                    state.unacked.insert(message, targets);
+                    drop(state);
+                    self.push_pending(&runtime, message);
+                    if sender_is_peer {
+                        // the per-message ack is its own request rather than a
+                        // reply to this one: `process` is only re-entered for
+                        // messages sent via `execute_rpc`, not for replies to it.
+                        runtime.execute_rpc(sender, Request::BroadcastOk { message });
+                    }
+                } else if sender_is_peer {
+                    // we already had this message, so `sender` is a redundant
+                    // push path for us; tell it to prune us from its push set.
+                    let prune = Request::Prune {
+                        peer: runtime.node_id().to_string(),
+                    };
+                    runtime.execute_rpc(sender, prune);
                 }
                 runtime.reply_ok(req).await?;
                 return Ok(());
             }
+            Ok(Request::BroadcastOk { message }) => {
+                // the peer that just acked, whether an internal gossip peer
+                // or the originating client (`apply_ack` no-ops for it since
+                // it was never a push target, though `record_ack` still
+                // raises its weight and emits `Acked`)
+                let peer = req.src.clone();
+                let mut state = self.state.lock().unwrap();
+                state.apply_ack(message, &peer);
+                drop(state);
+                self.record_ack(&peer);
+                return Ok(());
+            }
             Ok(Request::Topology { topology }) => {
                 let neighbours = topology.get(runtime.node_id()).unwrap();
-                self.state.lock().unwrap().neighbours = neighbours.clone();
+                let mut state = self.state.lock().unwrap();
+                state.neighbours = neighbours.clone();
+                state.push_peers = neighbours.iter().cloned().collect();
+                let mut rng = rand::rng();
+                state.active_push_peers =
+                    weighted_sample(neighbours, &state.weights, PUSH_FANOUT, &mut rng)
+                        .into_iter()
+                        .collect();
+                // `Topology` can be delivered more than once; only the first
+                // delivery should spawn the background loops, or a repeat
+                // would start a second set racing the first over this state.
+                let first_delivery = !state.background_tasks_started;
+                state.background_tasks_started = true;
+                drop(state);
                 info!("My neighbours are {:?}", neighbours);
+                if first_delivery {
+                    self.start_pull_timer(runtime.clone());
+                    self.start_push_rotation_timer();
+                    self.start_push_loop(runtime.clone());
+                    self.start_reping_timer(runtime.clone());
+                }
+                return runtime.reply_ok(req).await;
+            }
+            Ok(Request::Prune { peer }) => {
+                let mut state = self.state.lock().unwrap();
+                state.apply_prune(&peer);
+                return Ok(());
+            }
+            Ok(Request::Upsert {
+                key,
+                value,
+                version,
+                wallclock,
+            }) => {
+                self.upsert(StoreKey::Named(key), value, version, wallclock);
+                return runtime.reply_ok(req).await;
+            }
+            Ok(Request::PullRequest { filter }) => {
+                let sender = req.src.clone();
+                let (messages, entries) = self.missing_for(&filter);
+                // sent as a fresh request rather than a reply, so the
+                // requester's `process` actually runs the merge below
+                runtime.execute_rpc(sender, Request::PullResponse { messages, entries });
+                return runtime.reply_ok(req).await;
+            }
+            Ok(Request::PullResponse { messages, entries }) => {
+                let mut merged = 0;
+                for message in messages {
+                    if self.try_add(message, runtime.node_id()) {
+                        merged += 1;
+                    }
+                }
+                for (key, versioned) in entries {
+                    if self.upsert(
+                        StoreKey::Named(key),
+                        versioned.value,
+                        versioned.version,
+                        versioned.wallclock,
+                    ) {
+                        merged += 1;
+                    }
+                }
+                if merged > 0 {
+                    let state = self.state.lock().unwrap();
+                    let _ = state.events.send(GossipEvent::PullMerged { count: merged });
+                }
+                return Ok(());
+            }
+            Ok(Request::Ping { token }) => {
+                let sender = req.src.clone();
+                // sent as a fresh request rather than a reply, so the
+                // pinger's `process` actually runs the liveness update below
+                runtime.execute_rpc(sender, Request::Pong { token });
                 return runtime.reply_ok(req).await;
             }
+            Ok(Request::Pong { token }) => {
+                let sender = req.src.clone();
+                let mut state = self.state.lock().unwrap();
+                if let Some(entry) = state.ping_cache.get_mut(&sender) {
+                    if entry.pending_token == Some(token) {
+                        entry.last_pong = Some(Instant::now());
+                        entry.pending_token = None;
+                        entry.pending_since = None;
+                    }
+                }
+                state.unreachable.remove(&sender);
+                return Ok(());
+            }
             _ => runtime.exit(req),
         }
     }
 }
 
 impl GossipNode {
-    fn retry(&self, runtime: Runtime, msg: u64) {
+    /// The single long-lived task that retries every currently-unacked
+    /// message, started once per node rather than once per broadcast. Each
+    /// tick it retries whichever messages `push_pending` still has targets
+    /// for; `Broadcast` itself calls `push_pending` too, so the first send
+    /// goes out on receipt rather than waiting for this timer's next tick.
+    fn start_push_loop(&self, runtime: Runtime) {
         let node = self.clone();
 
-        // why is the background task necessary?
-        // because we need to retry sending the message until all neighbours have acknowledged it.
-        // if we don't do this, we will not be able to send the message to all neighbours.
         tokio::spawn(async move {
             loop {
-                {
+                sleep(Duration::from_secs(1)).await;
+
+                let pending: Vec<u64> = {
                     let state = node.state.lock().unwrap();
-                    if state.unacked.get(&msg).map_or(true, |un| un.is_empty()) {
-                        break;
-                    }
+                    state.unacked.keys().copied().collect()
+                };
+
+                for msg in pending {
+                    node.push_pending(&runtime, msg);
+                }
+            }
+        });
+    }
+
+    /// Retries `msg` against whichever of its remaining `unacked` targets are
+    /// still in the active push set (pruned or rotated-out peers drop out
+    /// automatically), penalizing a peer only once it's actually missed a
+    /// full `PUSH_MSG_TIMEOUT` window to ack a prior send — not merely for
+    /// still being unacked on this tick, which would punish a reliable peer
+    /// whose ack just hasn't arrived yet.
+    fn push_pending(&self, runtime: &Runtime, msg: u64) {
+        let mut state = self.state.lock().unwrap();
+        let Some(targets) = state.unacked.get(&msg).cloned() else {
+            return;
+        };
+        if targets.is_empty() {
+            state.unacked.remove(&msg);
+            state.send_times.remove(&msg);
+            return;
+        }
+
+        let eligible: Vec<String> = targets
+            .iter()
+            .filter(|peer| {
+                state.push_peers.contains(*peer)
+                    && state.active_push_peers.contains(*peer)
+                    && !state.unreachable.contains(*peer)
+            })
+            .cloned()
+            .collect();
 
-                    // we have unacked messages, so we will retry
-                    let mut neighbours: Vec<String> =
-                        state.unacked.get(&msg).unwrap().iter().cloned().collect();
+        let mut rng = rand::rng();
+        let selected = weighted_sample(&eligible, &state.weights, RANDOM_PEER_COUNT, &mut rng);
+        drop(state);
 
-                    // a per-thread random number generator to shuffle the order of neighbours randomly
+        let mut sent = Vec::new();
+        for neighbour in selected {
+            if self.ensure_live(runtime, &neighbour) {
+                runtime.execute_rpc(neighbour.clone(), Request::Broadcast { message: msg });
+                sent.push(neighbour);
+            }
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        let send_times = state.send_times.entry(msg).or_default();
+        let timed_out: Vec<String> = sent
+            .iter()
+            .filter(|peer| {
+                send_times
+                    .get(*peer)
+                    .is_some_and(|last| last.elapsed() >= PUSH_MSG_TIMEOUT)
+            })
+            .cloned()
+            .collect();
+        for peer in &sent {
+            send_times.insert(peer.clone(), now);
+        }
+        for peer in &timed_out {
+            let weight = state.weights.entry(peer.clone()).or_insert(1);
+            *weight = (*weight).saturating_sub(1).max(1);
+        }
+    }
+
+    /// Rotates one peer out of, and a fresh one into, the active push set
+    /// every `PUSH_MSG_TIMEOUT / 2`, keeping the overlay fresh as the topology
+    /// changes. Candidates are drawn from all `neighbours`, not just
+    /// `push_peers`, so a peer pruned for a redundant push path is lazily
+    /// re-admitted (into both `push_peers` and `active_push_peers`) the next
+    /// time it's rotated in, rather than staying excluded forever.
+    fn start_push_rotation_timer(&self) {
+        let node = self.clone();
+
+        tokio::spawn(async move {
+            loop {
+                sleep(PUSH_MSG_TIMEOUT / 2).await;
+
+                let mut state = node.state.lock().unwrap();
+                let mut rng = rand::rng();
+                state.rotate_active_push_peer(&mut rng);
+            }
+        });
+    }
+
+    /// Gates gossip on liveness: if `peer`'s pong is fresh, returns `true` so
+    /// the caller can proceed. Otherwise sends a `Ping` (if one isn't already
+    /// outstanding) and returns `false`, withholding gossip until the
+    /// matching `Pong` arrives. A peer that doesn't pong within `PING_TIMEOUT`
+    /// is marked unreachable until it does.
+    fn ensure_live(&self, runtime: &Runtime, peer: &str) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if state.is_reachable(peer) {
+            return true;
+        }
+        if state.has_live_pending_ping(peer) {
+            return false;
+        }
+        if state.ping_cache.get(peer).and_then(|entry| entry.pending_since).is_some() {
+            state.unreachable.insert(peer.to_string());
+        }
+
+        let token: u64 = rand::rng().random();
+        state.begin_ping(peer, token);
+        drop(state);
+
+        runtime.execute_rpc(peer.to_string(), Request::Ping { token });
+        false
+    }
+
+    /// Re-pings every peer currently marked unreachable. `unreachable` peers
+    /// are filtered out of the regular push/pull fanout before `ensure_live`
+    /// would ever be called on them, so this is the only path by which a
+    /// recovered peer clears `unreachable` and rejoins gossip.
+    fn start_reping_timer(&self, runtime: Runtime) {
+        let node = self.clone();
+
+        tokio::spawn(async move {
+            loop {
+                sleep(REPING_INTERVAL).await;
+
+                let unreachable: Vec<String> = {
+                    let state = node.state.lock().unwrap();
+                    state.unreachable.iter().cloned().collect()
+                };
+
+                for peer in unreachable {
+                    node.ensure_live(&runtime, &peer);
+                }
+            }
+        });
+    }
+
+    /// Rewards a peer that promptly acknowledged a message by raising its
+    /// selection weight, so future fanout favours reliable peers.
+    pub fn record_ack(&self, peer: &str) {
+        let mut state = self.state.lock().unwrap();
+        *state.weights.entry(peer.to_string()).or_insert(1) += 1;
+        let _ = state.events.send(GossipEvent::Acked {
+            peer: peer.to_string(),
+        });
+    }
+
+    /// Pins a peer's selection weight directly, e.g. so tests can make
+    /// weighted selection deterministic.
+    pub fn set_weight(&self, peer: &str, weight: u64) {
+        self.state
+            .lock()
+            .unwrap()
+            .weights
+            .insert(peer.to_string(), weight);
+    }
+
+    /// Periodically picks a random neighbour and asks it for anything our
+    /// `store` is missing, so a node that missed pushes (e.g. because it
+    /// was partitioned or just joined) eventually catches up.
+    fn start_pull_timer(&self, runtime: Runtime) {
+        let node = self.clone();
+
+        tokio::spawn(async move {
+            loop {
+                sleep(PULL_INTERVAL).await;
+
+                let target = {
+                    let state = node.state.lock().unwrap();
                     let mut rng = rand::rng();
-                    neighbours.shuffle(&mut rng);
+                    state
+                        .neighbours
+                        .iter()
+                        .filter(|peer| !state.unreachable.contains(*peer))
+                        .choose(&mut rng)
+                        .cloned()
+                };
 
-                    for neighbour in neighbours.into_iter().take(RANDOM_PEER_COUNT) {
-                        runtime.execute_rpc(neighbour, Request::Broadcast { message: msg });
-                    }
+                let Some(peer) = target else {
+                    continue;
+                };
+
+                if !node.ensure_live(&runtime, &peer) {
+                    continue;
                 }
 
-                // we run this background loop every 1 second.
-                sleep(Duration::from_secs(1)).await;
+                let filter = {
+                    let state = node.state.lock().unwrap();
+                    BloomFilter::from_values(state.store.keys().map(StoreKey::digest))
+                };
+
+                runtime.execute_rpc(peer, Request::PullRequest { filter });
             }
-            node.state.lock().unwrap().unacked.remove(&msg);
         });
     }
 
-    fn snapshot(&self) -> Vec<u64> {
-        self.state.lock().unwrap().seen.iter().copied().collect()
+    /// Returns every message id and CRDS entry `filter` reports as absent.
+    /// `entries` only ever holds `StoreKey::Named` entries, since message ids
+    /// live in their own `StoreKey::Message` variant and are reported via
+    /// `messages` instead.
+    fn missing_for(&self, filter: &BloomFilter) -> (Vec<u64>, Vec<(String, Versioned<Vec<u8>>)>) {
+        let state = self.state.lock().unwrap();
+        let messages = numeric_messages(&state.store)
+            .into_iter()
+            .filter(|value| !filter.contains(*value))
+            .collect();
+        let entries = state
+            .store
+            .iter()
+            .filter_map(|(key, versioned)| match key {
+                StoreKey::Named(_) if !filter.contains(key.digest()) => {
+                    Some((key.as_external(), versioned.clone()))
+                }
+                _ => None,
+            })
+            .collect();
+        (messages, entries)
     }
 
-    fn try_add(&self, value: u64) -> bool {
+    // `store` mixes `StoreKey::Message` and `StoreKey::Named` in one flat
+    // map, so keys use `namespaced` rather than `as_external`: a message id
+    // and a same-looking named key would otherwise collide once they're both
+    // just strings in the same `HashMap`.
+    fn snapshot(&self) -> (Vec<u64>, HashMap<String, Versioned<Vec<u8>>>) {
+        let state = self.state.lock().unwrap();
+        let messages = numeric_messages(&state.store);
+        let store = state
+            .store
+            .iter()
+            .map(|(key, versioned)| (key.namespaced(), versioned.clone()))
+            .collect();
+        (messages, store)
+    }
+
+    /// `Broadcast` is a thin wrapper over `Upsert`: the message is keyed by
+    /// `StoreKey::Message` at version 0, so it's stored, pulled, and merged
+    /// through the exact same CRDS path as any other `Upsert`.
+    fn try_add(&self, value: u64, node_id: &str) -> bool {
+        let changed = self.upsert(
+            StoreKey::Message(value),
+            value.to_be_bytes().to_vec(),
+            0,
+            0,
+        );
+        if changed {
+            let state = self.state.lock().unwrap();
+            let _ = state.events.send(GossipEvent::RumorReceived {
+                node_id: node_id.to_string(),
+                message: value,
+            });
+        }
+        changed
+    }
+
+    /// Merges an incoming key/value update into the CRDS store using
+    /// last-write-wins semantics, returning whether it changed local state.
+    fn upsert(&self, key: StoreKey, value: Vec<u8>, version: u64, wallclock: u64) -> bool {
+        let incoming = Versioned {
+            value,
+            version,
+            wallclock,
+        };
         let mut state = self.state.lock().unwrap();
-        if !state.seen.contains(&value) {
-            state.seen.insert(value);
-            return true;
+        if incoming.wins_over(state.store.get(&key)) {
+            state.store.insert(key, incoming);
+            true
+        } else {
+            false
         }
-        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weighted_sample_returns_count_unique_candidates() {
+        let candidates = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let weights = HashMap::new();
+        let mut rng = rand::rng();
+
+        let sample = weighted_sample(&candidates, &weights, 2, &mut rng);
+
+        assert_eq!(sample.len(), 2);
+        assert_ne!(sample[0], sample[1]);
+    }
+
+    #[test]
+    fn weighted_sample_favors_a_pinned_higher_weight() {
+        let candidates = vec!["reliable".to_string(), "flaky".to_string()];
+        let mut weights = HashMap::new();
+        weights.insert("reliable".to_string(), 1000);
+        weights.insert("flaky".to_string(), 1);
+        let mut rng = rand::rng();
+
+        let wins = (0..200)
+            .filter(|_| weighted_sample(&candidates, &weights, 1, &mut rng) == ["reliable"])
+            .count();
+
+        // overwhelmingly likely to win given a 1000x weight advantage; a
+        // generous threshold keeps this from being a flaky test
+        assert!(wins > 150, "reliable only won {wins}/200 samples");
+    }
+
+    #[test]
+    fn set_weight_pins_the_weight_used_for_selection() {
+        let node = GossipNode::new(vec!["a".to_string(), "b".to_string()]);
+        node.set_weight("a", 1000);
+        node.set_weight("b", 1);
+
+        let state = node.state.lock().unwrap();
+        assert_eq!(state.weights.get("a"), Some(&1000));
+        assert_eq!(state.weights.get("b"), Some(&1));
+    }
+
+    #[test]
+    fn apply_prune_removes_the_peer_and_its_unacked_bookkeeping() {
+        let mut state = NodeState::new(vec!["a".to_string(), "b".to_string()]);
+        state.push_peers = ["a".to_string(), "b".to_string()].into_iter().collect();
+        state
+            .unacked
+            .insert(1, ["a".to_string(), "b".to_string()].into_iter().collect());
+        state
+            .send_times
+            .entry(1)
+            .or_default()
+            .insert("a".to_string(), Instant::now());
+
+        state.apply_prune("a");
+
+        assert!(!state.push_peers.contains("a"));
+        assert!(state.push_peers.contains("b"));
+        assert_eq!(state.unacked.get(&1).unwrap().len(), 1);
+        assert!(!state.send_times.get(&1).unwrap().contains_key("a"));
+    }
+
+    #[test]
+    fn apply_prune_drops_a_message_once_its_last_target_is_pruned() {
+        let mut state = NodeState::new(vec!["a".to_string()]);
+        state.push_peers.insert("a".to_string());
+        state.unacked.insert(1, ["a".to_string()].into_iter().collect());
+
+        state.apply_prune("a");
+
+        assert!(!state.unacked.contains_key(&1));
+    }
+
+    #[test]
+    fn rotate_active_push_peer_keeps_the_active_set_size_stable() {
+        let mut state = NodeState::new(vec!["a".to_string(), "b".to_string(), "c".to_string()]);
+        state.active_push_peers = ["a".to_string(), "b".to_string()].into_iter().collect();
+        let mut rng = rand::rng();
+
+        state.rotate_active_push_peer(&mut rng);
+
+        assert_eq!(state.active_push_peers.len(), 2);
+    }
+
+    #[test]
+    fn rotate_active_push_peer_re_admits_a_previously_pruned_peer() {
+        // "pruned" here just means absent from `push_peers`/`active_push_peers`
+        // while still listed in `neighbours`, as `Prune` leaves it.
+        let mut state = NodeState::new(vec!["only".to_string()]);
+        state.push_peers.clear();
+        state.active_push_peers.clear();
+        let mut rng = rand::rng();
+
+        state.rotate_active_push_peer(&mut rng);
+
+        assert!(state.push_peers.contains("only"));
+        assert!(state.active_push_peers.contains("only"));
+    }
+
+    #[test]
+    fn apply_ack_clears_a_single_target_without_dropping_other_targets() {
+        let mut state = NodeState::new(vec!["a".to_string(), "b".to_string()]);
+        state
+            .unacked
+            .insert(1, ["a".to_string(), "b".to_string()].into_iter().collect());
+        state
+            .send_times
+            .entry(1)
+            .or_default()
+            .insert("a".to_string(), Instant::now());
+
+        state.apply_ack(1, "a");
+
+        assert_eq!(state.unacked.get(&1).unwrap().len(), 1);
+        assert!(!state.unacked.get(&1).unwrap().contains("a"));
+        assert!(!state.send_times.get(&1).unwrap().contains_key("a"));
+    }
+
+    #[test]
+    fn apply_ack_drops_bookkeeping_once_every_target_has_acked() {
+        let mut state = NodeState::new(vec!["a".to_string()]);
+        state.unacked.insert(1, ["a".to_string()].into_iter().collect());
+        state.send_times.entry(1).or_default().insert("a".to_string(), Instant::now());
+
+        state.apply_ack(1, "a");
+
+        assert!(!state.unacked.contains_key(&1));
+        assert!(!state.send_times.contains_key(&1));
+    }
+
+    #[test]
+    fn is_reachable_is_false_until_a_pong_is_recorded() {
+        let mut state = NodeState::new(vec!["a".to_string()]);
+        assert!(!state.is_reachable("a"));
+
+        state.ping_cache.entry("a".to_string()).or_default().last_pong = Some(Instant::now());
+
+        assert!(state.is_reachable("a"));
+    }
+
+    #[test]
+    fn begin_ping_is_seen_as_a_live_pending_ping_until_it_times_out() {
+        let mut state = NodeState::new(vec!["a".to_string()]);
+        assert!(!state.has_live_pending_ping("a"));
+
+        state.begin_ping("a", 42);
+
+        assert!(state.has_live_pending_ping("a"));
+        assert_eq!(state.ping_cache.get("a").unwrap().pending_token, Some(42));
     }
 }
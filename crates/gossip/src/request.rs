@@ -1,3 +1,5 @@
+use crate::bloom::BloomFilter;
+use crate::crds::Versioned;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -8,11 +10,59 @@ pub enum Request {
     Read {},
     ReadOk {
         messages: Vec<u64>,
+        store: HashMap<String, Versioned<Vec<u8>>>,
     },
     Broadcast {
         message: u64,
     },
+    /// Explicit per-message ack, sent to a gossip peer in addition to the
+    /// bare `reply_ok`, so the sender can tell exactly which message was
+    /// delivered rather than inferring it from having received *any* reply
+    /// to *a* `Broadcast`.
+    BroadcastOk {
+        message: u64,
+    },
     Topology {
         topology: HashMap<String, Vec<String>>,
     },
+
+    /// A generalized, last-write-wins keyed update. The numeric `Broadcast`
+    /// path is a thin wrapper over this (key = the message, version = 0), so
+    /// gossip can also overwrite existing keys instead of only ever
+    /// accumulating immutable ids.
+    Upsert {
+        key: String,
+        value: Vec<u8>,
+        version: u64,
+        wallclock: u64,
+    },
+
+    /// A compact summary of the sender's CRDS store keys (message ids and
+    /// hashed named keys), used
+    /// to pull whatever the receiver has that the sender is missing.
+    PullRequest {
+        filter: BloomFilter,
+    },
+    /// Everything the responder has that the requester's filter reported as
+    /// absent: bare message ids plus full keyed CRDS entries.
+    PullResponse {
+        messages: Vec<u64>,
+        entries: Vec<(String, Versioned<Vec<u8>>)>,
+    },
+
+    /// Sent by a node that received a `Broadcast` it had already seen, telling
+    /// the recipient that this path is redundant and should be removed from
+    /// its active push set.
+    Prune {
+        peer: String,
+    },
+
+    /// Liveness check sent to a peer whose last verified pong is stale;
+    /// gossip to that peer is withheld until the matching `Pong` returns.
+    Ping {
+        token: u64,
+    },
+    Pong {
+        token: u64,
+    },
 }